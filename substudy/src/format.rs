@@ -0,0 +1,60 @@
+//! A common abstraction over subtitle file formats, so callers can load
+//! either SRT or WebVTT without caring which one they actually have.
+
+use std::{fs::File, io::Read as _, path::Path};
+
+use anyhow::Context as _;
+
+use crate::{
+    decode::smart_decode,
+    srt::{Srt, SubtitleFile},
+    vtt::Vtt,
+    Result,
+};
+
+/// A subtitle file format, such as SRT or WebVTT, that can be parsed from
+/// and rendered back to plain text. Implemented by the zero-sized marker
+/// types [`Srt`] and [`Vtt`]; both formats share the same
+/// [`Subtitle`](crate::srt::Subtitle)/[`Period`](crate::time::Period)/
+/// [`SubtitleFile`] model.
+pub trait SubtitleFormat {
+    /// The filename extension most commonly used for this format, without
+    /// the leading dot.
+    const EXTENSION: &'static str;
+
+    /// Parse subtitle data in this format.
+    fn parse(data: &str) -> Result<SubtitleFile>;
+
+    /// Render a subtitle file in this format.
+    fn render(file: &SubtitleFile) -> String;
+
+    /// Does `data` look like it's written in this format? Used to sniff
+    /// content when the file extension is missing or untrustworthy.
+    fn sniff(data: &str) -> bool;
+}
+
+/// Load a subtitle file of unknown format from `path`, choosing between SRT
+/// and WebVTT by extension first, and falling back to sniffing the file's
+/// content if the extension doesn't tell us (or is missing).
+pub fn from_path_auto(path: &Path) -> Result<SubtitleFile> {
+    let mut file = File::open(path)
+        .with_context(|| format!("could not open {}", path.display()))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    let data = smart_decode(&bytes)
+        .with_context(|| format!("could not read {}", path.display()))?;
+
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let is_vtt = match extension.as_deref() {
+        Some(ext) if ext == Vtt::EXTENSION => true,
+        Some(ext) if ext == Srt::EXTENSION => false,
+        _ => Vtt::sniff(&data),
+    };
+
+    if is_vtt { Vtt::parse(&data) } else { Srt::parse(&data) }
+        .with_context(|| format!("could not parse {}", path.display()))
+}
@@ -0,0 +1,12 @@
+//! A library for reading, writing, and transforming subtitle files.
+
+pub mod clean;
+pub mod decode;
+pub mod format;
+pub mod lang;
+pub mod srt;
+pub mod time;
+pub mod vtt;
+
+/// This crate's standard result type.
+pub type Result<T> = anyhow::Result<T>;
@@ -1,5 +1,8 @@
 //! SRT-format subtitle support.
 
+mod lenient;
+mod span;
+
 use std::{fs::File, io::Read as _, path::Path};
 
 use anyhow::Context as _;
@@ -8,11 +11,14 @@ use serde::{Deserialize, Serialize};
 use crate::{
     clean::{clean_subtitle_file, strip_formatting},
     decode::smart_decode,
+    format::SubtitleFormat,
     lang::Lang,
     time::Period,
     Result,
 };
 
+pub use self::span::{to_srt_line, Position, Span};
+
 /// Format seconds using the standard SRT time format.
 pub fn format_time(time: f32) -> String {
     let (h, rem) = ((time / 3600.0).trunc(), time % 3600.0);
@@ -51,6 +57,15 @@ impl Subtitle {
     pub fn plain_text(&self) -> String {
         strip_formatting(&self.lines.join(" ")).into_owned()
     }
+
+    /// Parse each of `self.lines` into a structured representation of its
+    /// inline markup, preserving bold/italic/underline, `<font color=...>`,
+    /// and `{\an8}`-style positioning directives instead of flattening them
+    /// away the way [`Subtitle::plain_text`] does. Use [`to_srt_line`] to
+    /// rebuild valid SRT markup from the result.
+    pub fn styled_lines(&self) -> Vec<Vec<Span>> {
+        self.lines.iter().map(|line| span::parse_line(line)).collect()
+    }
 }
 
 /// The contents of an SRT-format subtitle file.
@@ -60,6 +75,18 @@ pub struct SubtitleFile {
     pub subtitles: Vec<Subtitle>,
 }
 
+/// A problem noticed while parsing a subtitle file with
+/// [`SubtitleFile::from_str_lenient`]. These are informational: the parser
+/// recovered and kept going, but callers may want to surface them to the
+/// user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    /// The 1-based line number the warning refers to.
+    pub line: usize,
+    /// A human-readable description of what looked wrong.
+    pub message: String,
+}
+
 impl SubtitleFile {
     /// Parse raw subtitle text into an appropriate structure.
     pub fn from_str(data: &str) -> Result<SubtitleFile> {
@@ -90,6 +117,86 @@ impl SubtitleFile {
         Ok(clean_subtitle_file(&raw)?)
     }
 
+    /// Parse raw subtitle text that doesn't necessarily follow the strict
+    /// `index / timestamp / lines / blank line` grammar used by
+    /// [`SubtitleFile::from_str`]. This recovers from common problems found
+    /// in the wild, such as a missing index, extra blank lines, or trailing
+    /// cue-settings text sharing a line with the timestamp, instead of
+    /// failing the whole parse. Anything it can't make sense of is skipped
+    /// and reported as a [`ParseWarning`] rather than raised as an error.
+    pub fn from_str_lenient(data: &str) -> (SubtitleFile, Vec<ParseWarning>) {
+        lenient::parse(data.trim_start_matches('\u{FEFF}'))
+    }
+
+    /// Parse the subtitle file found at the specified path in lenient mode.
+    /// See [`SubtitleFile::from_str_lenient`] for details.
+    pub fn from_path_lenient(path: &Path) -> Result<(SubtitleFile, Vec<ParseWarning>)> {
+        let mut file = File::open(path)
+            .with_context(|| format!("could not open {}", path.display()))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        let data = smart_decode(&bytes)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        Ok(SubtitleFile::from_str_lenient(&data))
+    }
+
+    /// Parse WebVTT-format subtitle text. See [`crate::vtt`] for how WebVTT
+    /// maps onto this same `Subtitle`/`Period`/`SubtitleFile` model.
+    pub fn from_vtt_str(data: &str) -> Result<SubtitleFile> {
+        crate::vtt::from_vtt_str(data)
+    }
+
+    /// Render this file as WebVTT-format subtitle text.
+    pub fn to_vtt_string(&self) -> String {
+        crate::vtt::to_vtt_string(self)
+    }
+
+    /// Load a subtitle file of unknown format, choosing between SRT and
+    /// WebVTT by extension and, failing that, by sniffing the file's
+    /// content.
+    pub fn from_path_auto(path: &Path) -> Result<SubtitleFile> {
+        crate::format::from_path_auto(path)
+    }
+
+    /// Move every cue in this file forward (or backward, if negative) by
+    /// `seconds`, clamping so that no period's begin time goes negative.
+    pub fn shift(&mut self, seconds: f32) {
+        for sub in &mut self.subtitles {
+            sub.period = clamp_nonnegative(sub.period.shift(seconds));
+        }
+    }
+
+    /// Rescale every cue's timing by `factor`, stretching or compressing
+    /// the whole file around t=0. This is the usual fix for subtitles that
+    /// drift because of a frame-rate conversion, e.g.
+    /// `rescale(25.0 / 24.0)` for a 25fps-to-24fps conversion.
+    pub fn rescale(&mut self, factor: f32) {
+        for sub in &mut self.subtitles {
+            if let Ok(period) = Period::new(sub.period.begin() * factor, sub.period.end() * factor)
+            {
+                sub.period = clamp_nonnegative(period);
+            }
+        }
+    }
+
+    /// Resync this file using two anchor points. `(old_a, new_a)` and
+    /// `(old_b, new_b)` each pair a timecode as it currently appears in
+    /// this file with the correct timecode it should have. We solve the
+    /// linear map `t' = scale*t + offset` implied by the two points, and
+    /// apply it to every period.
+    pub fn resync(&mut self, (old_a, new_a): (f32, f32), (old_b, new_b): (f32, f32)) {
+        let scale = (new_b - new_a) / (old_b - old_a);
+        let offset = new_a - scale * old_a;
+        for sub in &mut self.subtitles {
+            let begin = scale * sub.period.begin() + offset;
+            let end = scale * sub.period.end() + offset;
+            if let Ok(period) = Period::new(begin, end) {
+                sub.period = clamp_nonnegative(period);
+            }
+        }
+    }
+
     /// Convert subtitles to a string.
     pub fn to_string(&self) -> String {
         let subs: Vec<String> = self.subtitles.iter().map(|s| s.to_string()).collect();
@@ -112,6 +219,230 @@ impl SubtitleFile {
         let text = subs.join("\n");
         Lang::for_text(&text)
     }
+
+    /// Correct the timings of this subtitle file using `reference`, a
+    /// second, correctly-timed file (possibly in another language). This is
+    /// useful when you have a subtitle file that's out of sync and a second
+    /// file for the same video that isn't, and you want to fix the first one
+    /// without manually hunting for the right offset.
+    ///
+    /// We match purely on the time-span structure of speech, ignoring text:
+    /// for each subtitle, we search `search_window` seconds in either
+    /// direction (quantized to 10 ms steps) for the integer delta that makes
+    /// it overlap `reference` as much as possible, while charging
+    /// `split_penalty` whenever two consecutive subtitles end up with
+    /// different deltas. This biases the result towards shifting long runs
+    /// of subtitles uniformly, and only introduces a new offset partway
+    /// through the file when the overlap evidence is strong enough to be
+    /// worth it.
+    pub fn sync_to(
+        &self,
+        reference: &SubtitleFile,
+        split_penalty: f32,
+        search_window: f32,
+    ) -> SubtitleFile {
+        /// The size of a single step in our quantized search space.
+        const STEP: f32 = 0.01;
+
+        let steps = (search_window / STEP).round() as i64;
+        let deltas: Vec<f32> = (-steps..=steps).map(|i| i as f32 * STEP).collect();
+        if self.subtitles.is_empty() || deltas.is_empty() {
+            return self.clone();
+        }
+
+        // `overlap_with_reference` assumes `ref_spans` is sorted and
+        // non-overlapping, which isn't guaranteed of subtitles as authored,
+        // so sort and merge before handing them off.
+        let ref_spans: Vec<(f32, f32)> = reference
+            .subtitles
+            .iter()
+            .map(|s| (s.period.begin(), s.period.end()))
+            .collect();
+        let ref_spans = merge_overlapping_spans(ref_spans);
+
+        // `best[d]` is the best total rating for all subtitles seen so far,
+        // ending with the current subtitle assigned `deltas[d]`. `back[i][d]`
+        // records which delta the *previous* subtitle used to reach that
+        // rating, so we can walk the chain back afterwards.
+        let mut best = vec![0.0f32; deltas.len()];
+        let mut back = vec![vec![0usize; deltas.len()]; self.subtitles.len()];
+
+        for (i, sub) in self.subtitles.iter().enumerate() {
+            let (prev_best_idx, prev_best) = if i == 0 {
+                (0, 0.0)
+            } else {
+                best_index(&best, &deltas)
+            };
+
+            let mut next_best = vec![0.0f32; deltas.len()];
+            for (d, &delta) in deltas.iter().enumerate() {
+                let score = overlap_with_reference(
+                    sub.period.begin() + delta,
+                    sub.period.end() + delta,
+                    &ref_spans,
+                );
+                let keep_delta = if i == 0 { 0.0 } else { best[d] };
+                let switch_delta = prev_best - split_penalty;
+                if i == 0 || keep_delta >= switch_delta {
+                    next_best[d] = keep_delta + score;
+                    back[i][d] = d;
+                } else {
+                    next_best[d] = switch_delta + score;
+                    back[i][d] = prev_best_idx;
+                }
+            }
+            best = next_best;
+        }
+
+        // Walk the back-pointers from the best final delta to recover the
+        // chosen delta for every subtitle. If nothing ever overlapped
+        // `reference` at any candidate delta, every rating ties at zero;
+        // rather than pick an arbitrary (and, pre-tie-break, maximally
+        // negative) shift, leave the file alone.
+        let (mut d, rating) = best_index(&best, &deltas);
+        if rating <= 0.0 {
+            return self.clone();
+        }
+
+        let mut chosen = vec![0usize; self.subtitles.len()];
+        chosen[self.subtitles.len() - 1] = d;
+        for i in (1..self.subtitles.len()).rev() {
+            d = back[i][d];
+            chosen[i - 1] = d;
+        }
+
+        let mut result = self.clone();
+        for (sub, &d) in result.subtitles.iter_mut().zip(chosen.iter()) {
+            sub.period = sub.period.shift(deltas[d]);
+        }
+        result
+    }
+
+    /// Split this file into segments at the given `points` (in seconds),
+    /// returning `points.len() + 1` files. A subtitle whose period begins
+    /// before the first point goes in the first file, a subtitle whose
+    /// period begins between the first and second points goes in the
+    /// second, and so on. Indices are renumbered from 1 within each part.
+    ///
+    /// If `timeshift` is true, every part after the first has its periods
+    /// shifted back by that segment's start offset, so it lines up with a
+    /// correspondingly-split video clip. If false, the original timecodes
+    /// are kept. This is the inverse of [`AppendWithOffset::append_with_offset`].
+    pub fn split_at(&self, points: &[f32], timeshift: bool) -> Vec<SubtitleFile> {
+        let mut starts = vec![0.0f32];
+        starts.extend_from_slice(points);
+
+        let mut parts: Vec<Vec<Subtitle>> = vec![Vec::new(); starts.len()];
+        for sub in &self.subtitles {
+            let segment = starts
+                .iter()
+                .rposition(|&start| sub.period.begin() >= start)
+                .unwrap_or(0);
+            parts[segment].push(sub.clone());
+        }
+
+        parts
+            .into_iter()
+            .zip(starts.iter())
+            .map(|(subs, &start)| {
+                let mut next_index = 1;
+                let subtitles = subs
+                    .into_iter()
+                    .map(|mut sub| {
+                        sub.index = next_index;
+                        next_index += 1;
+                        if timeshift && start > 0.0 {
+                            sub.period = sub.period.shift(-start);
+                        }
+                        sub
+                    })
+                    .collect();
+                SubtitleFile { subtitles }
+            })
+            .collect()
+    }
+}
+
+/// Shift `period` forward, if needed, so that its begin time is no lower
+/// than zero, preserving its duration. A no-op if `period` is already
+/// non-negative.
+fn clamp_nonnegative(period: Period) -> Period {
+    if period.begin() < 0.0 {
+        period.shift(-period.begin())
+    } else {
+        period
+    }
+}
+
+/// Find the index and value of the largest entry in `ratings`. Ties are
+/// broken in favor of the delta closest to zero (using the matching entry
+/// in `deltas`), so a flat plateau of equal ratings — most commonly an
+/// all-zero plateau when nothing overlaps at any candidate delta — doesn't
+/// silently bias the result towards one end of the search window.
+fn best_index(ratings: &[f32], deltas: &[f32]) -> (usize, f32) {
+    ratings
+        .iter()
+        .enumerate()
+        .fold((0, f32::NEG_INFINITY), |best, (i, &rating)| {
+            if rating > best.1
+                || (rating == best.1 && deltas[i].abs() < deltas[best.0].abs())
+            {
+                (i, rating)
+            } else {
+                best
+            }
+        })
+}
+
+/// Sort `spans` by start time (the caller is assumed to not have done so)
+/// and merge any that overlap or touch, so the result satisfies the
+/// "sorted and non-overlapping" precondition of [`overlap_with_reference`].
+fn merge_overlapping_spans(mut spans: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<(f32, f32)> = Vec::with_capacity(spans.len());
+    for (begin, end) in spans {
+        match merged.last_mut() {
+            Some((_, last_end)) if begin <= *last_end => {
+                *last_end = last_end.max(end);
+            }
+            _ => merged.push((begin, end)),
+        }
+    }
+    merged
+}
+
+/// Total overlap, in seconds, between the span `[begin, end)` and all of
+/// `ref_spans`, which must be sorted by start time and non-overlapping.
+fn overlap_with_reference(begin: f32, end: f32, ref_spans: &[(f32, f32)]) -> f32 {
+    let start = ref_spans.partition_point(|&(_, ref_end)| ref_end <= begin);
+    ref_spans[start..]
+        .iter()
+        .take_while(|&&(ref_begin, _)| ref_begin < end)
+        .map(|&(ref_begin, ref_end)| (end.min(ref_end) - begin.max(ref_begin)).max(0.0))
+        .sum()
+}
+
+/// Marker type identifying the SRT subtitle format, for use with the
+/// [`SubtitleFormat`] trait.
+pub struct Srt;
+
+impl SubtitleFormat for Srt {
+    const EXTENSION: &'static str = "srt";
+
+    fn parse(data: &str) -> Result<SubtitleFile> {
+        SubtitleFile::from_str(data)
+    }
+
+    fn render(file: &SubtitleFile) -> String {
+        file.to_string()
+    }
+
+    fn sniff(data: &str) -> bool {
+        !data
+            .trim_start_matches('\u{FEFF}')
+            .trim_start()
+            .starts_with("WEBVTT")
+    }
 }
 
 /// Interface for time-based formats that can be appended with an offset.
@@ -280,6 +611,229 @@ Text
         assert_eq!(srt.subtitles[0].period.end(), 1.001);
     }
 
+    #[test]
+    fn subtitle_styled_lines() {
+        let sub = Subtitle {
+            index: 1,
+            period: Period::new(1.0, 2.0).unwrap(),
+            lines: vec!["Plain <b>bold</b>".to_string()],
+        };
+        let lines = sub.styled_lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0][0].text, "Plain ");
+        assert!(!lines[0][0].bold);
+        assert_eq!(lines[0][1].text, "bold");
+        assert!(lines[0][1].bold);
+    }
+
+    #[test]
+    fn sync_to_reference() {
+        // Our subtitles are 2.0s late relative to the reference.
+        let srt = SubtitleFile::from_str(
+            "1
+00:00:12,000 --> 00:00:14,000
+A
+
+2
+00:00:22,000 --> 00:00:24,000
+B
+
+3
+00:00:32,000 --> 00:00:34,000
+C
+",
+        )
+        .unwrap();
+        let reference = SubtitleFile::from_str(
+            "1
+00:00:10,000 --> 00:00:12,000
+A
+
+2
+00:00:20,000 --> 00:00:22,000
+B
+
+3
+00:00:30,000 --> 00:00:32,000
+C
+",
+        )
+        .unwrap();
+
+        let synced = srt.sync_to(&reference, 5.0, 10.0);
+        for (sub, reference_sub) in synced.subtitles.iter().zip(reference.subtitles.iter()) {
+            assert_eq!(sub.period, reference_sub.period);
+        }
+    }
+
+    #[test]
+    fn sync_to_leaves_file_unchanged_when_reference_is_empty() {
+        let srt = SubtitleFile::from_str(
+            "1
+00:00:12,000 --> 00:00:14,000
+A
+",
+        )
+        .unwrap();
+        let reference = SubtitleFile { subtitles: vec![] };
+
+        let synced = srt.sync_to(&reference, 5.0, 10.0);
+        assert_eq!(synced, srt);
+    }
+
+    #[test]
+    fn sync_to_leaves_file_unchanged_when_nothing_overlaps() {
+        let srt = SubtitleFile::from_str(
+            "1
+00:00:12,000 --> 00:00:14,000
+A
+",
+        )
+        .unwrap();
+        // Far outside the search window, so no candidate delta can ever
+        // make this overlap `srt`.
+        let reference = SubtitleFile::from_str(
+            "1
+00:05:00,000 --> 00:05:02,000
+A
+",
+        )
+        .unwrap();
+
+        let synced = srt.sync_to(&reference, 5.0, 10.0);
+        assert_eq!(synced, srt);
+    }
+
+    #[test]
+    fn sync_to_handles_unsorted_overlapping_reference_spans() {
+        let srt = SubtitleFile {
+            subtitles: vec![Subtitle {
+                index: 1,
+                period: Period::new(12.0, 14.0).unwrap(),
+                lines: vec!["A".to_string()],
+            }],
+        };
+        // Out of time order, and overlapping each other: the reference
+        // spans should still get sorted and merged before we search for
+        // the best delta.
+        let reference = SubtitleFile {
+            subtitles: vec![
+                Subtitle {
+                    index: 2,
+                    period: Period::new(10.5, 12.0).unwrap(),
+                    lines: vec!["other".to_string()],
+                },
+                Subtitle {
+                    index: 1,
+                    period: Period::new(10.0, 11.0).unwrap(),
+                    lines: vec!["A".to_string()],
+                },
+            ],
+        };
+
+        let synced = srt.sync_to(&reference, 5.0, 5.0);
+        assert_eq!(synced.subtitles[0].period.begin(), 10.0);
+    }
+
+    #[test]
+    fn split_at_points() {
+        let srt = SubtitleFile::from_str(
+            "1
+00:00:05,000 --> 00:00:06,000
+A
+
+2
+00:00:15,000 --> 00:00:16,000
+B
+
+3
+00:00:25,000 --> 00:00:26,000
+C
+",
+        )
+        .unwrap();
+
+        let parts = srt.split_at(&[10.0, 20.0], true);
+        assert_eq!(parts.len(), 3);
+
+        assert_eq!(parts[0].subtitles.len(), 1);
+        assert_eq!(parts[0].subtitles[0].index, 1);
+        assert_eq!(parts[0].subtitles[0].period.begin(), 5.0);
+
+        assert_eq!(parts[1].subtitles.len(), 1);
+        assert_eq!(parts[1].subtitles[0].index, 1);
+        assert_eq!(parts[1].subtitles[0].period.begin(), 5.0);
+
+        assert_eq!(parts[2].subtitles.len(), 1);
+        assert_eq!(parts[2].subtitles[0].index, 1);
+        assert_eq!(parts[2].subtitles[0].period.begin(), 5.0);
+    }
+
+    #[test]
+    fn split_at_points_without_timeshift() {
+        let srt = SubtitleFile::from_str(
+            "1
+00:00:05,000 --> 00:00:06,000
+A
+
+2
+00:00:15,000 --> 00:00:16,000
+B
+",
+        )
+        .unwrap();
+
+        let parts = srt.split_at(&[10.0], false);
+        assert_eq!(parts[1].subtitles[0].period.begin(), 15.0);
+    }
+
+    #[test]
+    fn shift_clamps_to_nonnegative() {
+        let mut srt = SubtitleFile::from_str(
+            "1
+00:00:01,000 --> 00:00:02,000
+A
+",
+        )
+        .unwrap();
+        srt.shift(-5.0);
+        assert_eq!(srt.subtitles[0].period.begin(), 0.0);
+        assert_eq!(srt.subtitles[0].period.end(), 1.0);
+    }
+
+    #[test]
+    fn rescale_stretches_timings() {
+        let mut srt = SubtitleFile::from_str(
+            "1
+00:00:10,000 --> 00:00:20,000
+A
+",
+        )
+        .unwrap();
+        srt.rescale(2.0);
+        assert_eq!(srt.subtitles[0].period.begin(), 20.0);
+        assert_eq!(srt.subtitles[0].period.end(), 40.0);
+    }
+
+    #[test]
+    fn resync_solves_linear_map() {
+        let mut srt = SubtitleFile::from_str(
+            "1
+00:00:10,000 --> 00:00:11,000
+A
+
+2
+00:00:20,000 --> 00:00:21,000
+B
+",
+        )
+        .unwrap();
+        // Anchor 10s -> 12s and 20s -> 24s implies t' = 1.2*t.
+        srt.resync((10.0, 12.0), (20.0, 24.0));
+        assert_eq!(srt.subtitles[0].period.begin(), 12.0);
+        assert_eq!(srt.subtitles[1].period.begin(), 24.0);
+    }
+
     #[test]
     fn detect_language() {
         let path_es = Path::new("fixtures/sample.es.srt");
@@ -0,0 +1,236 @@
+//! A recovering, state-machine-based parser for malformed or freeform SRT
+//! files, used by [`super::SubtitleFile::from_str_lenient`].
+
+use crate::time::Period;
+
+use super::{ParseWarning, Subtitle, SubtitleFile};
+
+/// Where we are within the current cue while scanning lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Between cues, looking for the start of the next one: either an index
+    /// line or a timestamp line.
+    Initial,
+    /// We saw an index line and are now looking for its timestamp.
+    ExpectTimestamp,
+    /// We just parsed a timestamp and are looking for the first line of the
+    /// cue's body.
+    FirstBodyLine,
+    /// We have at least one body line, and keep accumulating more until a
+    /// blank line (or the end of input) ends the cue.
+    RestOfBody,
+    /// We just saw a blank line while inside a cue's body.
+    LastWasBlank,
+}
+
+pub(super) fn parse(data: &str) -> (SubtitleFile, Vec<ParseWarning>) {
+    let mut state = State::Initial;
+    let mut warnings = Vec::new();
+    let mut subtitles = Vec::new();
+
+    let mut pending_index: Option<usize> = None;
+    let mut pending_period: Option<Period> = None;
+    let mut pending_lines: Vec<String> = Vec::new();
+
+    for (offset, raw_line) in data.lines().enumerate() {
+        let line_no = offset + 1;
+        let line = raw_line.trim_end_matches('\r');
+        let trimmed = line.trim();
+
+        match state {
+            State::Initial => {
+                if trimmed.is_empty() {
+                    // Skip leading (or inter-cue) blank lines.
+                } else if let Some(period) = parse_timestamp_line(trimmed) {
+                    warnings.push(ParseWarning {
+                        line: line_no,
+                        message: "missing cue index; starting a new cue from this timestamp"
+                            .to_string(),
+                    });
+                    pending_period = Some(period);
+                    state = State::FirstBodyLine;
+                } else if let Ok(index) = trimmed.parse::<usize>() {
+                    pending_index = Some(index);
+                    state = State::ExpectTimestamp;
+                } else {
+                    warnings.push(ParseWarning {
+                        line: line_no,
+                        message: "expected a subtitle index or timestamp".to_string(),
+                    });
+                }
+            }
+
+            State::ExpectTimestamp => {
+                if let Some(period) = parse_timestamp_line(trimmed) {
+                    pending_period = Some(period);
+                    state = State::FirstBodyLine;
+                } else {
+                    warnings.push(ParseWarning {
+                        line: line_no,
+                        message: "expected a timestamp after the index".to_string(),
+                    });
+                    pending_index = None;
+                    state = State::Initial;
+                }
+            }
+
+            State::FirstBodyLine => {
+                if trimmed.is_empty() {
+                    // A cue with no body text at all.
+                    finish_cue(
+                        &mut subtitles,
+                        &mut pending_index,
+                        &mut pending_period,
+                        &mut pending_lines,
+                    );
+                    state = State::Initial;
+                } else {
+                    pending_lines.push(line.to_string());
+                    state = State::RestOfBody;
+                }
+            }
+
+            State::RestOfBody => {
+                if trimmed.is_empty() {
+                    state = State::LastWasBlank;
+                } else {
+                    pending_lines.push(line.to_string());
+                }
+            }
+
+            State::LastWasBlank => {
+                if trimmed.is_empty() {
+                    // Coalesce consecutive blank lines into a single cue
+                    // break.
+                } else if let Some(period) = parse_timestamp_line(trimmed) {
+                    finish_cue(
+                        &mut subtitles,
+                        &mut pending_index,
+                        &mut pending_period,
+                        &mut pending_lines,
+                    );
+                    warnings.push(ParseWarning {
+                        line: line_no,
+                        message: "missing cue index; starting a new cue from this timestamp"
+                            .to_string(),
+                    });
+                    pending_period = Some(period);
+                    state = State::FirstBodyLine;
+                } else if let Ok(index) = trimmed.parse::<usize>() {
+                    finish_cue(
+                        &mut subtitles,
+                        &mut pending_index,
+                        &mut pending_period,
+                        &mut pending_lines,
+                    );
+                    pending_index = Some(index);
+                    state = State::ExpectTimestamp;
+                } else {
+                    // No index and no timestamp: finish the current cue
+                    // and skip this line, since there's no period to show
+                    // it at.
+                    finish_cue(
+                        &mut subtitles,
+                        &mut pending_index,
+                        &mut pending_period,
+                        &mut pending_lines,
+                    );
+                    warnings.push(ParseWarning {
+                        line: line_no,
+                        message: "missing index and timestamp; skipping this line"
+                            .to_string(),
+                    });
+                    state = State::Initial;
+                }
+            }
+        }
+    }
+
+    finish_cue(
+        &mut subtitles,
+        &mut pending_index,
+        &mut pending_period,
+        &mut pending_lines,
+    );
+
+    (SubtitleFile { subtitles }, warnings)
+}
+
+/// Finish accumulating the current cue, if it has a timestamp, and push it
+/// onto `subtitles`. A cue with no timestamp (nothing was ever recovered)
+/// is silently dropped, since there's no period to show it at.
+fn finish_cue(
+    subtitles: &mut Vec<Subtitle>,
+    index: &mut Option<usize>,
+    period: &mut Option<Period>,
+    lines: &mut Vec<String>,
+) {
+    if let Some(period) = period.take() {
+        let index = index.take().unwrap_or(subtitles.len() + 1);
+        subtitles.push(Subtitle {
+            index,
+            period,
+            lines: std::mem::take(lines),
+        });
+    }
+    *index = None;
+    lines.clear();
+}
+
+/// Look for a `begin --> end` timestamp anywhere in `line`, rather than
+/// requiring the whole line to be exactly a timestamp, so that trailing cue
+/// settings or a leading index on the same line don't prevent a match.
+fn parse_timestamp_line(line: &str) -> Option<Period> {
+    let arrow = line.find("-->")?;
+    let begin = parse_timestamp(line[..arrow].split_whitespace().next_back()?)?;
+    let end = parse_timestamp(line[arrow + 3..].split_whitespace().next()?)?;
+    if end > begin {
+        Period::new(begin, end).ok()
+    } else {
+        // Tolerate zero-duration cues, as the strict grammar does for
+        // Aeneas-style output, and treat negative durations the same way
+        // (the strict grammar rejects those).
+        Period::new(begin, begin + 0.001).ok()
+    }
+}
+
+/// Parse a single `HH:MM:SS,mmm` (or `HH:MM:SS.mmm`) timestamp.
+fn parse_timestamp(text: &str) -> Option<f32> {
+    let mut parts = text.splitn(3, ':');
+    let hh: f32 = parts.next()?.parse().ok()?;
+    let mm: f32 = parts.next()?.parse().ok()?;
+    let ss: f32 = parts.next()?.replace(',', ".").parse().ok()?;
+    Some(hh * 3600.0 + mm * 60.0 + ss)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recovers_missing_index() {
+        let data = "00:00:01,000 --> 00:00:02,000\nHello\n\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+        let (srt, warnings) = parse(data);
+        assert_eq!(srt.subtitles.len(), 2);
+        assert_eq!(srt.subtitles[0].lines, vec!["Hello".to_string()]);
+        assert_eq!(srt.subtitles[1].lines, vec!["World".to_string()]);
+        assert!(!warnings.is_empty());
+    }
+
+    #[test]
+    fn tolerates_extra_blank_lines() {
+        let data = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n\n\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+        let (srt, _warnings) = parse(data);
+        assert_eq!(srt.subtitles.len(), 2);
+        assert_eq!(srt.subtitles[1].index, 2);
+    }
+
+    #[test]
+    fn tolerates_trailing_cue_settings() {
+        let data = "1\n00:00:01,000 --> 00:00:02,000 X1:40 X2:600\nHello\n";
+        let (srt, _warnings) = parse(data);
+        assert_eq!(srt.subtitles.len(), 1);
+        assert_eq!(srt.subtitles[0].period.begin(), 1.0);
+        assert_eq!(srt.subtitles[0].period.end(), 2.0);
+    }
+}
@@ -0,0 +1,322 @@
+//! A structured representation of SRT inline markup, used by
+//! [`super::Subtitle::styled_lines`] as an alternative to flattening
+//! formatting away with [`crate::clean::strip_formatting`].
+
+use serde::{Deserialize, Serialize};
+
+/// A positioning override found in a subtitle line, taken from the ASS-style
+/// override codes some SRT files embed in braces.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Position {
+    /// A `{\anN}` alignment override, using the numpad layout (1 = bottom
+    /// left, 9 = top right).
+    Alignment(u8),
+    /// An explicit `{\pos(x,y)}` coordinate override.
+    Coordinates(f32, f32),
+}
+
+/// A run of text from a subtitle line, together with the formatting that
+/// applies to it. Produced by [`parse_line`], and the inverse of
+/// [`to_srt_line`].
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+pub struct Span {
+    /// The text of this run, with all markup removed.
+    pub text: String,
+    /// Set by a surrounding `<b>`.
+    pub bold: bool,
+    /// Set by a surrounding `<i>`.
+    pub italic: bool,
+    /// Set by a surrounding `<u>`.
+    pub underline: bool,
+    /// Set by a surrounding `<font color="...">`.
+    pub color: Option<String>,
+    /// Set if this span is the first one on a line that began with a
+    /// positioning directive.
+    pub position: Option<Position>,
+}
+
+/// Which style a `<b>`/`<i>`/`<u>` tag toggles.
+#[derive(Debug, Clone, Copy)]
+enum Style {
+    Bold,
+    Italic,
+    Underline,
+}
+
+/// What a single `<...>` tag means, once we've looked past its angle
+/// brackets.
+enum Tag {
+    Open(Style),
+    Close(Style),
+    FontOpen(String),
+    FontClose,
+    /// A tag we don't understand; pass it through verbatim as text.
+    Unknown,
+}
+
+/// Parse one line of subtitle text into a sequence of styled spans.
+pub fn parse_line(line: &str) -> Vec<Span> {
+    let (position, rest) = take_leading_position(line);
+
+    let mut spans = Vec::new();
+    let mut bold = false;
+    let mut italic = false;
+    let mut underline = false;
+    let mut color: Option<String> = None;
+    let mut text = String::new();
+
+    let mut remaining = rest;
+    while let Some(lt) = remaining.find('<') {
+        text.push_str(&remaining[..lt]);
+        let after_lt = &remaining[lt + 1..];
+        let Some(gt) = after_lt.find('>') else {
+            // No closing bracket: treat the rest of the line as plain text.
+            text.push_str(&remaining[lt..]);
+            remaining = "";
+            break;
+        };
+        let tag = &after_lt[..gt];
+        remaining = &after_lt[gt + 1..];
+
+        match classify_tag(tag) {
+            Tag::Open(Style::Bold) => {
+                flush(&mut spans, &mut text, bold, italic, underline, &color);
+                bold = true;
+            }
+            Tag::Close(Style::Bold) => {
+                flush(&mut spans, &mut text, bold, italic, underline, &color);
+                bold = false;
+            }
+            Tag::Open(Style::Italic) => {
+                flush(&mut spans, &mut text, bold, italic, underline, &color);
+                italic = true;
+            }
+            Tag::Close(Style::Italic) => {
+                flush(&mut spans, &mut text, bold, italic, underline, &color);
+                italic = false;
+            }
+            Tag::Open(Style::Underline) => {
+                flush(&mut spans, &mut text, bold, italic, underline, &color);
+                underline = true;
+            }
+            Tag::Close(Style::Underline) => {
+                flush(&mut spans, &mut text, bold, italic, underline, &color);
+                underline = false;
+            }
+            Tag::FontOpen(new_color) => {
+                flush(&mut spans, &mut text, bold, italic, underline, &color);
+                color = Some(new_color);
+            }
+            Tag::FontClose => {
+                flush(&mut spans, &mut text, bold, italic, underline, &color);
+                color = None;
+            }
+            Tag::Unknown => {
+                text.push('<');
+                text.push_str(tag);
+                text.push('>');
+            }
+        }
+    }
+    text.push_str(remaining);
+    flush(&mut spans, &mut text, bold, italic, underline, &color);
+
+    match (position, spans.first_mut()) {
+        (Some(position), Some(first)) => first.position = Some(position),
+        (Some(position), None) => spans.push(Span {
+            position: Some(position),
+            ..Span::default()
+        }),
+        (None, _) => {}
+    }
+    spans
+}
+
+/// Rebuild a line of valid SRT markup from spans produced by [`parse_line`].
+pub fn to_srt_line(spans: &[Span]) -> String {
+    let mut out = String::new();
+    for position in spans.iter().filter_map(|span| span.position) {
+        out.push_str(&position_tag(position));
+    }
+    for span in spans {
+        let mut text = span.text.clone();
+        if let Some(color) = &span.color {
+            text = format!("<font color=\"{color}\">{text}</font>");
+        }
+        if span.underline {
+            text = format!("<u>{text}</u>");
+        }
+        if span.italic {
+            text = format!("<i>{text}</i>");
+        }
+        if span.bold {
+            text = format!("<b>{text}</b>");
+        }
+        out.push_str(&text);
+    }
+    out
+}
+
+fn position_tag(position: Position) -> String {
+    match position {
+        Position::Alignment(n) => format!("{{\\an{n}}}"),
+        Position::Coordinates(x, y) => format!("{{\\pos({x},{y})}}"),
+    }
+}
+
+/// Flush the current run of text into a new span with the given style, if
+/// there's any text to flush.
+fn flush(
+    spans: &mut Vec<Span>,
+    text: &mut String,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    color: &Option<String>,
+) {
+    if !text.is_empty() {
+        spans.push(Span {
+            text: std::mem::take(text),
+            bold,
+            italic,
+            underline,
+            color: color.clone(),
+            position: None,
+        });
+    }
+}
+
+/// Classify the contents of a `<...>` tag, ignoring case.
+fn classify_tag(tag: &str) -> Tag {
+    let lower = tag.trim().to_ascii_lowercase();
+    match lower.as_str() {
+        "b" => Tag::Open(Style::Bold),
+        "/b" => Tag::Close(Style::Bold),
+        "i" => Tag::Open(Style::Italic),
+        "/i" => Tag::Close(Style::Italic),
+        "u" => Tag::Open(Style::Underline),
+        "/u" => Tag::Close(Style::Underline),
+        "/font" => Tag::FontClose,
+        _ if lower.starts_with("font") => {
+            extract_color(tag).map(Tag::FontOpen).unwrap_or(Tag::Unknown)
+        }
+        _ => Tag::Unknown,
+    }
+}
+
+/// Pull the value out of a `color="..."` (or unquoted `color=...`) attribute
+/// inside a `<font ...>` tag.
+fn extract_color(tag: &str) -> Option<String> {
+    let idx = tag.to_ascii_lowercase().find("color")?;
+    let after_color = &tag[idx + "color".len()..];
+    let eq = after_color.find('=')?;
+    let value = after_color[eq + 1..].trim_start();
+    match value.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = value[1..].find(quote)?;
+            Some(value[1..1 + end].to_string())
+        }
+        _ => {
+            let end = value
+                .find(|c: char| c.is_whitespace())
+                .unwrap_or(value.len());
+            Some(value[..end].to_string())
+        }
+    }
+}
+
+/// Strip a single leading `{\anN}` or `{\pos(x,y)}` override code, if
+/// present, and return it along with the rest of the line.
+fn take_leading_position(line: &str) -> (Option<Position>, &str) {
+    if !line.starts_with('{') {
+        return (None, line);
+    }
+    let Some(end) = line.find('}') else {
+        return (None, line);
+    };
+    let code = &line[1..end];
+    let rest = &line[end + 1..];
+
+    if let Some(n) = code.strip_prefix("\\an").and_then(|n| n.parse().ok()) {
+        return (Some(Position::Alignment(n)), rest);
+    }
+    if let Some(coords) = code.strip_prefix("\\pos(").and_then(|s| s.strip_suffix(')')) {
+        if let Some((x, y)) = coords.split_once(',') {
+            if let (Ok(x), Ok(y)) = (x.trim().parse(), y.trim().parse()) {
+                return (Some(Position::Coordinates(x, y)), rest);
+            }
+        }
+    }
+    (None, line)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_plain_text() {
+        let spans = parse_line("Hello, world!");
+        assert_eq!(
+            spans,
+            vec![Span {
+                text: "Hello, world!".to_string(),
+                ..Span::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_nested_styles() {
+        let spans = parse_line("<i>Hello, <b>world</b>!</i>");
+        assert_eq!(
+            spans,
+            vec![
+                Span {
+                    text: "Hello, ".to_string(),
+                    italic: true,
+                    ..Span::default()
+                },
+                Span {
+                    text: "world".to_string(),
+                    italic: true,
+                    bold: true,
+                    ..Span::default()
+                },
+                Span {
+                    text: "!".to_string(),
+                    italic: true,
+                    ..Span::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_font_color() {
+        let spans = parse_line(r#"<font color="#ff0000">Red</font>"#);
+        assert_eq!(
+            spans,
+            vec![Span {
+                text: "Red".to_string(),
+                color: Some("#ff0000".to_string()),
+                ..Span::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_alignment_directive() {
+        let spans = parse_line(r"{\an8}Top center");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].position, Some(Position::Alignment(8)));
+        assert_eq!(spans[0].text, "Top center");
+    }
+
+    #[test]
+    fn round_trips_through_srt_markup() {
+        let line = "<b><i>Hello</i></b>";
+        let spans = parse_line(line);
+        assert_eq!(to_srt_line(&spans), line);
+    }
+}
@@ -0,0 +1,212 @@
+//! WebVTT-format subtitle support.
+//!
+//! WebVTT reuses the [`Subtitle`]/[`Period`]/[`SubtitleFile`] model from the
+//! SRT parser, but differs from it in a few concrete ways: an optional
+//! `WEBVTT` header line, a `.` rather than a `,` millisecond separator,
+//! optional cue identifiers and `NOTE` blocks, and cue settings (`align:`,
+//! `position:`, `line:`, ...) trailing the timestamps. We parse what we can
+//! of all of these and ignore cue settings, since we have nowhere to put
+//! them in the shared model yet.
+
+use anyhow::{anyhow, Context as _};
+
+use crate::{
+    format::SubtitleFormat,
+    srt::{format_time, Subtitle, SubtitleFile},
+    time::Period,
+    Result,
+};
+
+/// Marker type identifying the WebVTT subtitle format, for use with the
+/// [`SubtitleFormat`] trait. See the [module docs](self) for format
+/// details.
+pub struct Vtt;
+
+impl SubtitleFormat for Vtt {
+    const EXTENSION: &'static str = "vtt";
+
+    fn parse(data: &str) -> Result<SubtitleFile> {
+        from_vtt_str(data)
+    }
+
+    fn render(file: &SubtitleFile) -> String {
+        to_vtt_string(file)
+    }
+
+    fn sniff(data: &str) -> bool {
+        data.trim_start_matches('\u{FEFF}')
+            .trim_start()
+            .starts_with("WEBVTT")
+    }
+}
+
+/// Parse WebVTT-format subtitle text.
+pub fn from_vtt_str(data: &str) -> Result<SubtitleFile> {
+    let data = data.trim_start_matches('\u{FEFF}');
+    let mut lines = data.lines().peekable();
+
+    // An optional `WEBVTT` header, possibly with trailing metadata text on
+    // the same line.
+    if matches!(lines.peek(), Some(first) if first.starts_with("WEBVTT")) {
+        lines.next();
+    }
+
+    let mut subtitles = Vec::new();
+    let mut next_index = 1;
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if trimmed.starts_with("NOTE") {
+            // A NOTE block runs until the next blank line.
+            for note_line in lines.by_ref() {
+                if note_line.trim().is_empty() {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        // This is either a cue identifier or the timing line itself.
+        let timing_line = if trimmed.contains("-->") {
+            trimmed.to_string()
+        } else {
+            lines
+                .next()
+                .map(|l| l.trim().to_string())
+                .ok_or_else(|| anyhow!("expected a timestamp after cue identifier {trimmed:?}"))?
+        };
+
+        let (begin, end) = parse_cue_timing(&timing_line)
+            .with_context(|| format!("could not parse VTT cue timing {timing_line:?}"))?;
+        let end = if begin == end {
+            // As with SRT, tolerate zero-length cues, which tools like
+            // Aeneas generate, rather than rejecting the whole file.
+            end + 0.001
+        } else {
+            end
+        };
+        let period =
+            Period::new(begin, end).map_err(|_| anyhow!("invalid cue timing {timing_line:?}"))?;
+
+        let mut cue_lines = Vec::new();
+        for body_line in lines.by_ref() {
+            if body_line.trim().is_empty() {
+                break;
+            }
+            cue_lines.push(body_line.to_string());
+        }
+
+        subtitles.push(Subtitle {
+            index: next_index,
+            period,
+            lines: cue_lines,
+        });
+        next_index += 1;
+    }
+
+    Ok(SubtitleFile { subtitles })
+}
+
+/// Render a subtitle file as WebVTT.
+pub fn to_vtt_string(file: &SubtitleFile) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for (i, sub) in file.subtitles.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "{} --> {}\n{}\n",
+            format_vtt_time(sub.period.begin()),
+            format_vtt_time(sub.period.end()),
+            sub.lines.join("\n"),
+        ));
+    }
+    out
+}
+
+/// Parse a `begin --> end [settings...]` timing line, ignoring any cue
+/// settings that follow the timestamps.
+fn parse_cue_timing(line: &str) -> Result<(f32, f32)> {
+    let arrow = line.find("-->").ok_or_else(|| anyhow!("missing '-->'"))?;
+    let begin = parse_vtt_time(line[..arrow].trim())?;
+    let end_text = line[arrow + 3..]
+        .trim()
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("missing end time"))?;
+    let end = parse_vtt_time(end_text)?;
+    Ok((begin, end))
+}
+
+/// Parse a WebVTT timestamp. Unlike SRT, the hours field is optional and
+/// milliseconds are separated with `.` rather than `,`.
+fn parse_vtt_time(text: &str) -> Result<f32> {
+    let fields: Vec<&str> = text.split(':').collect();
+    let (hh, mm, ss) = match fields.as_slice() {
+        [mm, ss] => ("0", *mm, *ss),
+        [hh, mm, ss] => (*hh, *mm, *ss),
+        _ => return Err(anyhow!("malformed timestamp {text:?}")),
+    };
+    let hh: f32 = hh.parse().with_context(|| format!("bad hours in {text:?}"))?;
+    let mm: f32 = mm.parse().with_context(|| format!("bad minutes in {text:?}"))?;
+    let ss: f32 = ss.parse().with_context(|| format!("bad seconds in {text:?}"))?;
+    Ok(hh * 3600.0 + mm * 60.0 + ss)
+}
+
+/// Format seconds using the WebVTT time format (`.` for milliseconds).
+fn format_vtt_time(time: f32) -> String {
+    format_time(time).replace(',', ".")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_header_and_cues() {
+        let data = "WEBVTT
+
+1
+00:00:01.000 --> 00:00:02.500
+Hello
+
+00:00:03.000 --> 00:00:04.000 align:center
+World
+";
+        let vtt = from_vtt_str(data).unwrap();
+        assert_eq!(vtt.subtitles.len(), 2);
+        assert_eq!(vtt.subtitles[0].period.begin(), 1.0);
+        assert_eq!(vtt.subtitles[0].period.end(), 2.5);
+        assert_eq!(vtt.subtitles[0].lines, vec!["Hello".to_string()]);
+        assert_eq!(vtt.subtitles[1].lines, vec!["World".to_string()]);
+    }
+
+    #[test]
+    fn skips_note_blocks() {
+        let data = "WEBVTT
+
+NOTE
+This is a comment
+spanning multiple lines
+
+00:00:01.000 --> 00:00:02.000
+Hello
+";
+        let vtt = from_vtt_str(data).unwrap();
+        assert_eq!(vtt.subtitles.len(), 1);
+    }
+
+    #[test]
+    fn round_trips_to_vtt_string() {
+        let data = "WEBVTT
+
+00:00:01.000 --> 00:00:02.500
+Hello
+";
+        let vtt = from_vtt_str(data).unwrap();
+        assert_eq!(to_vtt_string(&vtt), data);
+    }
+}